@@ -3,9 +3,45 @@ use clap::{Arg, Command, command};
 pub fn build_cli() -> Command {
 	command!()
 		.help_template("{name} {version} - {about}\nUSAGE: {usage}\nOPTIONS:\n{options}")
-		.arg(Arg::new("input").value_name("FILE").help("Input rclone-encrypted file").required(true))
-		.arg(Arg::new("output").short('o').long("output").value_name("FILE").help("Output decrypted file").required(true))
-		.arg(Arg::new("password").short('p').long("password").value_name("PASSWORD").help("Encryption password").required(true))
-		.arg(Arg::new("salt").short('s').long("salt").value_name("SALT").help("Salt used for encryption").required(true))
+		.arg(Arg::new("input").value_name("FILE").help("Input rclone-encrypted file (plaintext file with --encrypt) or, with --dir, directory").required(true))
+		.arg(Arg::new("output").short('o').long("output").value_name("FILE").help("Output decrypted file (rclone-encrypted file with --encrypt) or, with --dir, directory").required(true))
+		.arg(
+			Arg::new("password")
+				.short('p')
+				.long("password")
+				.value_name("PASSWORD")
+				.help("Encryption password")
+				.required_unless_present("config"),
+		)
+		.arg(
+			Arg::new("salt")
+				.short('s')
+				.long("salt")
+				.value_name("SALT")
+				.help("Salt used for encryption")
+				.required_unless_present("config"),
+		)
+		.arg(
+			Arg::new("config")
+				.long("config")
+				.value_name("FILE")
+				.help("rclone.conf to read the password/salt from instead of --password/--salt")
+				.requires("remote"),
+		)
+		.arg(
+			Arg::new("remote")
+				.long("remote")
+				.value_name("NAME")
+				.help("Name of the crypt remote in --config to use")
+				.requires("config"),
+		)
 		.arg(Arg::new("verbose").short('v').long("verbose").help("Enable verbose output").action(clap::ArgAction::SetTrue))
+		.arg(Arg::new("encrypt").long("encrypt").help("Encrypt instead of decrypt, producing an rclone-compatible file").action(clap::ArgAction::SetTrue))
+		.arg(
+			Arg::new("dir")
+				.long("dir")
+				.help("Treat input/output as directory trees: decrypt filenames and mirror the decrypted tree")
+				.action(clap::ArgAction::SetTrue)
+				.conflicts_with("encrypt"),
+		)
 }
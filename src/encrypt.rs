@@ -0,0 +1,109 @@
+use crate::decrypt::{CHUNK_SIZE, NONCE_SIZE, RCLONE_MAGIC, derive_key, increment_nonce, parse_salt, read_chunk};
+use crate::error::{DecryptionError, DecryptionResult};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+pub struct RcloneEncryptor {
+	password: String,
+	salt: Vec<u8>,
+}
+
+impl RcloneEncryptor {
+	pub fn new(password: String, salt: String) -> DecryptionResult<Self> {
+		Ok(RcloneEncryptor { password, salt: parse_salt(&salt)? })
+	}
+
+	/// Encrypt `input_path` into `output_path`. Thin wrapper around [`Self::encrypt_writer`]
+	/// that opens the files and reports the paths once done.
+	pub fn encrypt_file<P: AsRef<Path>>(&self, input_path: P, output_path: P, verbose: bool) -> DecryptionResult<()> {
+		let mut input_file = BufReader::new(File::open(&input_path)?);
+		let mut output_file = BufWriter::new(File::create(&output_path)?);
+		self.encrypt_writer(&mut input_file, &mut output_file, verbose)?;
+		println!("Successfully encrypted {} to {}", input_path.as_ref().display(), output_path.as_ref().display());
+		Ok(())
+	}
+
+	/// Stream-encrypt a reader into an rclone-compatible writer, one
+	/// CHUNK_SIZE block at a time so peak memory is a single chunk.
+	pub fn encrypt_writer<R: Read, W: Write>(&self, reader: &mut R, writer: &mut W, verbose: bool) -> DecryptionResult<()> {
+		// Derive key using the same scrypt parameters rclone uses for decryption
+		let key = derive_key(&self.password, &self.salt)?;
+		if verbose {
+			println!("Debug: Key derived successfully ({} bytes)", key.len());
+		};
+		// Initialize sodiumoxide for NaCl secretbox
+		sodiumoxide::init().map_err(|_| DecryptionError::InvalidPassword)?;
+		let secretbox_key = sodiumoxide::crypto::secretbox::Key::from_slice(&key).ok_or(DecryptionError::InvalidPassword)?;
+
+		// Fresh random nonce for this file
+		let mut chunk_nonce = [0u8; NONCE_SIZE];
+		sodiumoxide::randombytes::randombytes_into(&mut chunk_nonce);
+		if verbose {
+			println!("Debug: Generated nonce (24 bytes): {:?}", chunk_nonce);
+		};
+
+		writer.write_all(RCLONE_MAGIC)?;
+		writer.write_all(&chunk_nonce)?;
+
+		// Stream the plaintext one CHUNK_SIZE block at a time, sealing and
+		// writing each chunk straight out so peak memory is a single chunk.
+		let mut plain_buf = vec![0u8; CHUNK_SIZE];
+		let mut chunk_index = 0usize;
+		loop {
+			let read = read_chunk(reader, &mut plain_buf)?;
+			if read == 0 {
+				break;
+			}
+			let nonce_obj = sodiumoxide::crypto::secretbox::Nonce::from_slice(&chunk_nonce).ok_or(DecryptionError::InvalidFormat)?;
+			let sealed = sodiumoxide::crypto::secretbox::seal(&plain_buf[..read], &nonce_obj, &secretbox_key);
+			writer.write_all(&sealed)?;
+			if verbose {
+				println!("Debug: Sealed chunk {} ({} plaintext bytes -> {} bytes)", chunk_index, read, sealed.len());
+			};
+			increment_nonce(&mut chunk_nonce);
+
+			chunk_index += 1;
+			if read < plain_buf.len() {
+				// Short read means we just consumed the final chunk.
+				break;
+			}
+		}
+
+		writer.flush()?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::decrypt::RcloneDecryptor;
+
+	#[test]
+	fn round_trip_across_multiple_chunks() {
+		// Bigger than CHUNK_SIZE so the loop wraps around and exercises nonce increment.
+		let plaintext: Vec<u8> = (0..CHUNK_SIZE * 2 + 123).map(|i| (i % 256) as u8).collect();
+
+		let encryptor = RcloneEncryptor::new("testpassword".to_string(), "testsalt".to_string()).unwrap();
+		let mut ciphertext = Vec::new();
+		encryptor.encrypt_writer(&mut plaintext.as_slice(), &mut ciphertext, false).unwrap();
+
+		let decryptor = RcloneDecryptor::new("testpassword".to_string(), "testsalt".to_string()).unwrap();
+		let mut decrypted = Vec::new();
+		decryptor.decrypt_reader(&mut ciphertext.as_slice(), &mut decrypted, false).unwrap();
+
+		assert_eq!(decrypted, plaintext);
+	}
+
+	#[test]
+	fn wrong_password_fails_cleanly() {
+		let encryptor = RcloneEncryptor::new("testpassword".to_string(), "testsalt".to_string()).unwrap();
+		let mut ciphertext = Vec::new();
+		encryptor.encrypt_writer(&mut b"hello, world".as_slice(), &mut ciphertext, false).unwrap();
+
+		let decryptor = RcloneDecryptor::new("wrongpassword".to_string(), "testsalt".to_string()).unwrap();
+		let mut decrypted = Vec::new();
+		assert!(decryptor.decrypt_reader(&mut ciphertext.as_slice(), &mut decrypted, false).is_err());
+	}
+}
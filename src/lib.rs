@@ -0,0 +1,11 @@
+pub mod cli;
+pub mod decrypt;
+pub mod encrypt;
+pub mod error;
+pub mod names;
+pub mod rclone_config;
+
+pub use crate::decrypt::RcloneDecryptor;
+pub use crate::encrypt::RcloneEncryptor;
+pub use crate::error::{DecryptionError, DecryptionResult};
+pub use crate::names::NameCipher;
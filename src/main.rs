@@ -1,10 +1,9 @@
-mod cli;
-mod decrypt;
-mod error;
-
-use crate::cli::build_cli;
-use crate::decrypt::RcloneDecryptor;
-use crate::error::DecryptionResult;
+use rclonedecrypt::cli::build_cli;
+use rclonedecrypt::decrypt::{derive_keys, parse_salt};
+use rclonedecrypt::error::{DecryptionError, DecryptionResult};
+use rclonedecrypt::rclone_config;
+use rclonedecrypt::{NameCipher, RcloneDecryptor, RcloneEncryptor};
+use std::path::Path;
 use std::process;
 
 fn main() {
@@ -18,21 +17,72 @@ fn run() -> DecryptionResult<()> {
 	let matches = build_cli().get_matches();
 	let input_file = matches.get_one::<String>("input").unwrap();
 	let output_file = matches.get_one::<String>("output").unwrap();
-	let password = matches.get_one::<String>("password").unwrap();
-	let salt = matches.get_one::<String>("salt").unwrap();
 	let verbose = matches.get_flag("verbose");
+	let encrypt = matches.get_flag("encrypt");
+	let dir = matches.get_flag("dir");
+
+	let (password, salt) = match (matches.get_one::<String>("config"), matches.get_one::<String>("remote")) {
+		(Some(config_path), Some(remote)) => {
+			let crypt_remote = rclone_config::load_crypt_remote(config_path, remote)?;
+			if verbose {
+				println!("Debug: Read crypt remote \"{}\" from {}", remote, config_path);
+			}
+			(crypt_remote.password, crypt_remote.salt)
+		}
+		_ => {
+			let password = matches.get_one::<String>("password").ok_or(DecryptionError::InvalidPassword)?;
+			let salt = matches.get_one::<String>("salt").ok_or(DecryptionError::InvalidFormat)?;
+			(password.clone(), salt.clone())
+		}
+	};
+	let password = &password;
+	let salt = &salt;
 	if verbose {
 		println!("Input file: {}", input_file);
 		println!("Output file: {}", output_file);
 		println!("Password: (provided)");
 		println!("Salt: {}", &salt);
 	}
-	let decryptor = RcloneDecryptor::new(password.to_string(), salt.to_string())?;
-	if verbose {
-		println!("Debug: Using salt: {} bytes", decryptor.get_salt_debug().len());
+	if dir {
+		let salt_bytes = parse_salt(salt)?;
+		let keys = derive_keys(password, &salt_bytes)?;
+		let decryptor = RcloneDecryptor::new(password.to_string(), salt.to_string())?;
+		let names = NameCipher::new(keys.name_key, keys.name_tweak);
+		println!("Decrypting directory tree {} to {}...", input_file, output_file);
+		decrypt_tree(&decryptor, &names, Path::new(input_file), Path::new(output_file), verbose)?;
+		println!("Directory tree decryption completed successfully!");
+	} else if encrypt {
+		let encryptor = RcloneEncryptor::new(password.to_string(), salt.to_string())?;
+		println!("Encrypting using NaCl SecretBox (XSalsa20 + Poly1305)...");
+		encryptor.encrypt_file(input_file, output_file, verbose)?;
+		println!("Encryption completed successfully!");
+	} else {
+		let decryptor = RcloneDecryptor::new(password.to_string(), salt.to_string())?;
+		if verbose {
+			println!("Debug: Using salt: {} bytes", decryptor.get_salt_debug().len());
+		}
+		println!("Decrypting using NaCl SecretBox (XSalsa20 + Poly1305)...");
+		decryptor.decrypt_file(input_file, output_file, verbose)?;
+		println!("Decryption completed successfully!");
+	}
+	Ok(())
+}
+
+/// Walk `input_dir`, decrypting each entry's name and mirroring the decrypted
+/// tree (files and sub-directories) into `output_dir`.
+fn decrypt_tree(decryptor: &RcloneDecryptor, names: &NameCipher, input_dir: &Path, output_dir: &Path, verbose: bool) -> DecryptionResult<()> {
+	std::fs::create_dir_all(output_dir)?;
+	for entry in std::fs::read_dir(input_dir)? {
+		let entry = entry?;
+		let encrypted_name = entry.file_name();
+		let encrypted_name = encrypted_name.to_str().ok_or(DecryptionError::InvalidFormat)?;
+		let real_name = names.decrypt_segment(encrypted_name)?;
+		let output_path = output_dir.join(real_name);
+		if entry.file_type()?.is_dir() {
+			decrypt_tree(decryptor, names, &entry.path(), &output_path, verbose)?;
+		} else {
+			decryptor.decrypt_file(entry.path(), output_path, verbose)?;
+		}
 	}
-	println!("Decrypting using NaCl SecretBox (XSalsa20 + Poly1305)...");
-	decryptor.decrypt_file(input_file, output_file)?;
-	println!("Decryption completed successfully!");
 	Ok(())
 }
@@ -0,0 +1,179 @@
+use crate::decrypt::{NAME_KEY_SIZE, NAME_TWEAK_SIZE};
+use crate::error::{DecryptionError, DecryptionResult};
+use aes::Aes256;
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+
+const BLOCK_SIZE: usize = 16;
+/// rclone's filename alphabet: lowercase a-z2-7, no padding (a custom base32 variant).
+const BASE32_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Decrypts rclone-encrypted path segments (file and directory names).
+pub struct NameCipher {
+	name_key: [u8; NAME_KEY_SIZE],
+	name_tweak: [u8; NAME_TWEAK_SIZE],
+}
+
+impl NameCipher {
+	pub fn new(name_key: [u8; NAME_KEY_SIZE], name_tweak: [u8; NAME_TWEAK_SIZE]) -> Self {
+		NameCipher { name_key, name_tweak }
+	}
+
+	/// Decrypt a single encrypted path segment back to its plaintext name.
+	pub fn decrypt_segment(&self, segment: &str) -> DecryptionResult<String> {
+		let encrypted = base32_decode(segment)?;
+		let padded = eme_decrypt(&self.name_key, &self.name_tweak, &encrypted)?;
+		let name = unpad(&padded)?;
+		String::from_utf8(name.to_vec()).map_err(|_| DecryptionError::InvalidFormat)
+	}
+}
+
+/// Strip rclone's trailing PKCS#7-style padding: the last byte of the
+/// decrypted block holds the padding count, applied to a multiple of
+/// `BLOCK_SIZE` bytes (rclone's `unpad` in `backend/crypt/cipher.go`).
+fn unpad(buf: &[u8]) -> DecryptionResult<&[u8]> {
+	let n = *buf.last().ok_or(DecryptionError::InvalidFormat)? as usize;
+	if n == 0 || n > BLOCK_SIZE || n > buf.len() {
+		return Err(DecryptionError::InvalidFormat);
+	}
+	Ok(&buf[..buf.len() - n])
+}
+
+/// Decode rclone's base32 filename alphabet (lowercase a-z2-7, unpadded).
+fn base32_decode(s: &str) -> DecryptionResult<Vec<u8>> {
+	let mut bits: u32 = 0;
+	let mut bit_count = 0u32;
+	let mut out = Vec::with_capacity(s.len() * 5 / 8);
+	for c in s.bytes() {
+		let value = BASE32_ALPHABET.iter().position(|&b| b == c).ok_or(DecryptionError::InvalidFormat)? as u32;
+		bits = (bits << 5) | value;
+		bit_count += 5;
+		if bit_count >= 8 {
+			bit_count -= 8;
+			out.push((bits >> bit_count) as u8);
+		}
+	}
+	Ok(out)
+}
+
+/// XOR two 16-byte blocks.
+fn xor_blocks(a: &[u8; BLOCK_SIZE], b: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+	let mut out = [0u8; BLOCK_SIZE];
+	for i in 0..BLOCK_SIZE {
+		out[i] = a[i] ^ b[i];
+	}
+	out
+}
+
+/// Double (multiply by x) a 16-byte block in GF(2^128) using the standard
+/// reduction polynomial, as required by the EME "L"/"M" tables.
+fn double(block: &mut [u8; BLOCK_SIZE]) {
+	let carry = block[0] & 0x80 != 0;
+	for i in 0..BLOCK_SIZE - 1 {
+		block[i] = (block[i] << 1) | (block[i + 1] >> 7);
+	}
+	block[BLOCK_SIZE - 1] <<= 1;
+	if carry {
+		block[BLOCK_SIZE - 1] ^= 0x87;
+	}
+}
+
+/// EME (ECB-Mix-ECB, Halevi-Rogaway) decrypts `ciphertext`, which must be a
+/// whole number of 16-byte blocks (rclone segments are at most 128 blocks).
+fn eme_decrypt(name_key: &[u8; NAME_KEY_SIZE], tweak: &[u8; NAME_TWEAK_SIZE], ciphertext: &[u8]) -> DecryptionResult<Vec<u8>> {
+	if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(BLOCK_SIZE) {
+		return Err(DecryptionError::InvalidFormat);
+	}
+	let cipher = Aes256::new(GenericArray::from_slice(name_key));
+	let decrypt_block = |block: [u8; BLOCK_SIZE]| -> [u8; BLOCK_SIZE] {
+		let mut buf = GenericArray::clone_from_slice(&block);
+		cipher.decrypt_block(&mut buf);
+		let mut out = [0u8; BLOCK_SIZE];
+		out.copy_from_slice(&buf);
+		out
+	};
+	let encrypt_block = |block: [u8; BLOCK_SIZE]| -> [u8; BLOCK_SIZE] {
+		let mut buf = GenericArray::clone_from_slice(&block);
+		cipher.encrypt_block(&mut buf);
+		let mut out = [0u8; BLOCK_SIZE];
+		out.copy_from_slice(&buf);
+		out
+	};
+
+	let block_count = ciphertext.len() / BLOCK_SIZE;
+	// L-table: L_0 = AES(0) always uses the forward direction, regardless of
+	// whether the overall EME transform is encrypting or decrypting.
+	let mut l = encrypt_block([0u8; BLOCK_SIZE]);
+	let mut l_table = Vec::with_capacity(block_count);
+	for _ in 0..block_count {
+		l_table.push(l);
+		double(&mut l);
+	}
+
+	// CC_i = AES^{-1}(C_i XOR L_i).
+	let mut cc = Vec::with_capacity(block_count);
+	for i in 0..block_count {
+		let mut block = [0u8; BLOCK_SIZE];
+		block.copy_from_slice(&ciphertext[i * BLOCK_SIZE..(i + 1) * BLOCK_SIZE]);
+		cc.push(decrypt_block(xor_blocks(&block, &l_table[i])));
+	}
+
+	// SC = XOR of all CC_i, folding the tweak into the mixing value once,
+	// before it's doubled and XORed into every block but the first - not
+	// onto PP_1 directly, which only canceled out for single-block names.
+	let mut sc = [0u8; BLOCK_SIZE];
+	for block in &cc {
+		sc = xor_blocks(&sc, block);
+	}
+	let sp = decrypt_block(sc);
+	let mut s = xor_blocks(&xor_blocks(&sp, &sc), tweak);
+
+	let mut pp = vec![[0u8; BLOCK_SIZE]; block_count];
+	let mut pp_rest = [0u8; BLOCK_SIZE];
+	for i in 1..block_count {
+		double(&mut s);
+		pp[i] = xor_blocks(&cc[i], &s);
+		pp_rest = xor_blocks(&pp_rest, &pp[i]);
+	}
+	pp[0] = xor_blocks(&sp, &pp_rest);
+
+	let mut plaintext = vec![0u8; ciphertext.len()];
+	for i in 0..block_count {
+		let block = decrypt_block(pp[i]);
+		let plain = xor_blocks(&block, &l_table[i]);
+		plaintext[i * BLOCK_SIZE..(i + 1) * BLOCK_SIZE].copy_from_slice(&plain);
+	}
+	Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// `name_key`/`name_tweak` derived from scrypt("testpassword", "testsalt"),
+	// and "mq75fdgfi7pnubwcsgkjmjksfa" is the EME+base32 encoding of
+	// "hello.txt" padded with rclone's pad(), computed independently in
+	// Python (cryptography's AES-ECB + scrypt) since no `rclone` binary was
+	// available in this sandbox to generate the vector directly.
+	#[test]
+	fn decrypt_segment_matches_known_vector() {
+		let name_key = [152, 231, 61, 104, 172, 90, 57, 71, 97, 193, 232, 26, 223, 86, 233, 238, 243, 153, 39, 24, 2, 180, 155, 207, 252, 71, 239, 51, 32, 61, 195, 174];
+		let name_tweak = [103, 180, 76, 231, 200, 96, 21, 232, 17, 165, 216, 169, 104, 252, 10, 232];
+		let cipher = NameCipher::new(name_key, name_tweak);
+		assert_eq!(cipher.decrypt_segment("mq75fdgfi7pnubwcsgkjmjksfa").unwrap(), "hello.txt");
+	}
+
+	// Same key/tweak, but a name long enough to pad out to 4 blocks (64
+	// bytes), to exercise the multi-block mixing path that the single-block
+	// vector above can't: a 16-byte name's SC/SP happen to cancel out even
+	// with the tweak XORed onto the wrong term, so only a >15-byte name
+	// actually catches a mis-placed tweak.
+	#[test]
+	fn decrypt_segment_matches_known_vector_multi_block() {
+		let name_key = [152, 231, 61, 104, 172, 90, 57, 71, 97, 193, 232, 26, 223, 86, 233, 238, 243, 153, 39, 24, 2, 180, 155, 207, 252, 71, 239, 51, 32, 61, 195, 174];
+		let name_tweak = [103, 180, 76, 231, 200, 96, 21, 232, 17, 165, 216, 169, 104, 252, 10, 232];
+		let cipher = NameCipher::new(name_key, name_tweak);
+		let segment = "vopd463p5nz23bkfgm6nqmupulmrewzvc5be3vhujawh66em4ohqbqulvxfdr3t22357teg3pri46gbzcc7o5b7ksdccab2wrjyulky";
+		assert_eq!(cipher.decrypt_segment(segment).unwrap(), "this-is-a-much-longer-filename-that-spans-two-blocks.txt");
+	}
+}
@@ -0,0 +1,114 @@
+use crate::error::{DecryptionError, DecryptionResult};
+use aes::Aes256;
+use aes::cipher::{KeyIvInit, StreamCipher, generic_array::GenericArray};
+use base64::{Engine as _, engine::general_purpose};
+use ctr::Ctr128BE;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// rclone's fixed, publicly known AES-256 key used to "obscure" (not secure!)
+/// config values such as crypt passwords. Identical across all installs.
+const OBSCURE_KEY: [u8; 32] = [
+	0x9c, 0x93, 0x5b, 0x48, 0x73, 0x0a, 0x55, 0x4d, 0x6b, 0xfd, 0x7c, 0x63, 0xc8, 0x86, 0xa9, 0x2b, 0xd3, 0x90, 0x19, 0x8e, 0xb8, 0x12, 0x8a,
+	0xfb, 0xf4, 0xde, 0x16, 0x2b, 0x8b, 0x95, 0xf6, 0x38,
+];
+
+/// The password and salt for a `type = crypt` remote, revealed from rclone.conf.
+pub struct CryptRemote {
+	pub password: String,
+	pub salt: String,
+}
+
+/// Read `config_path`, find the `[remote]` section, check it's a crypt
+/// remote, and reveal its `password`/`password2` (salt) fields.
+pub fn load_crypt_remote<P: AsRef<Path>>(config_path: P, remote: &str) -> DecryptionResult<CryptRemote> {
+	let contents = fs::read_to_string(config_path)?;
+	let section = find_section(&contents, remote).ok_or(DecryptionError::InvalidFormat)?;
+	if section.get("type").map(String::as_str) != Some("crypt") {
+		return Err(DecryptionError::InvalidFormat);
+	}
+	let obscured_password = section.get("password").ok_or(DecryptionError::InvalidFormat)?;
+	let password = reveal(obscured_password)?;
+	let salt = match section.get("password2") {
+		Some(obscured_salt) if !obscured_salt.is_empty() => reveal(obscured_salt)?,
+		_ => String::new(),
+	};
+	Ok(CryptRemote { password, salt })
+}
+
+/// Find the `key = value` fields of the `[name]` section in an rclone.conf-style INI file.
+fn find_section(contents: &str, name: &str) -> Option<HashMap<String, String>> {
+	let header = format!("[{}]", name);
+	let mut in_section = false;
+	let mut found = false;
+	let mut fields = HashMap::new();
+	for line in contents.lines() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+			continue;
+		}
+		if line.starts_with('[') && line.ends_with(']') {
+			if found {
+				break;
+			}
+			in_section = line == header;
+			found = in_section;
+			continue;
+		}
+		if in_section {
+			if let Some((key, value)) = line.split_once('=') {
+				fields.insert(key.trim().to_string(), value.trim().to_string());
+			}
+		}
+	}
+	if found { Some(fields) } else { None }
+}
+
+/// Reverse rclone's config "obscure": base64-url-decode, split off the
+/// leading 16-byte AES-CTR IV, and decrypt the rest with the fixed key.
+fn reveal(obscured: &str) -> DecryptionResult<String> {
+	let data = general_purpose::URL_SAFE_NO_PAD.decode(obscured)?;
+	if data.len() < 16 {
+		return Err(DecryptionError::InvalidFormat);
+	}
+	let (iv, ciphertext) = data.split_at(16);
+	let mut plaintext = ciphertext.to_vec();
+	let mut cipher = Ctr128BE::<Aes256>::new(GenericArray::from_slice(&OBSCURE_KEY), GenericArray::from_slice(iv));
+	cipher.apply_keystream(&mut plaintext);
+	String::from_utf8(plaintext).map_err(|_| DecryptionError::InvalidFormat)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn obscure(plain: &str, iv: [u8; 16]) -> String {
+		let mut ciphertext = plain.as_bytes().to_vec();
+		let mut cipher = Ctr128BE::<Aes256>::new(GenericArray::from_slice(&OBSCURE_KEY), GenericArray::from_slice(&iv));
+		cipher.apply_keystream(&mut ciphertext);
+		let mut data = iv.to_vec();
+		data.extend_from_slice(&ciphertext);
+		general_purpose::URL_SAFE_NO_PAD.encode(data)
+	}
+
+	#[test]
+	fn reveal_round_trips_an_obscured_value() {
+		let obscured = obscure("hunter2", [7u8; 16]);
+		assert_eq!(reveal(&obscured).unwrap(), "hunter2");
+	}
+
+	#[test]
+	fn load_crypt_remote_defaults_salt_to_empty_when_no_password2() {
+		let obscured_password = obscure("mypassword", [3u8; 16]);
+		let config = format!("[remote]\ntype = crypt\npassword = {}\n", obscured_password);
+		let path = std::env::temp_dir().join(format!("rclonedecrypt_test_{}.conf", std::process::id()));
+		std::fs::write(&path, config).unwrap();
+
+		let result = load_crypt_remote(&path, "remote").unwrap();
+		std::fs::remove_file(&path).ok();
+
+		assert_eq!(result.password, "mypassword");
+		assert_eq!(result.salt, "");
+	}
+}